@@ -0,0 +1,173 @@
+//! Generic alignment-roulette machinery, extracted from the `factorial`
+//! family in `main.rs`. Given any `fn(Input) -> Output` kernel, [`align_roulette!`]
+//! stamps out a family of byte-identical copies — each forced to a distinct
+//! alignment and padded with a different number of leading nops so the
+//! linker places it at a different address — and reports the spread of
+//! measured minimum runtimes across them. That turns "is my benchmark gain
+//! real or just placement luck?" into a one-line check for any kernel, not
+//! just the bundled `factorial` demo.
+//!
+//! Families are declared as an explicit `pad => align` list rather than a
+//! bare copy count, matching how the rest of the crate declares function
+//! families (see `define_multiple!` in `main.rs`): macro_rules has no way to
+//! synthesize an arbitrary-length numeric range on its own.
+//!
+//! **The kernel must be `#[inline(always)]`.** Exactly like `factorial` in
+//! `main.rs`, `$kernel`'s body only ends up duplicated into every wrapper if
+//! the compiler is forced to inline it at each call site. A kernel that is
+//! merely called from each wrapper is free to stay a single out-of-line
+//! symbol shared by all copies — the wrappers' own addresses would still
+//! differ (thanks to the nop padding), but the reported spread would then be
+//! wrapper/call-overhead noise, not the kernel's own alignment sensitivity.
+//! [`align_roulette!`] sanity-checks for the degenerate case where the
+//! linker folded wrapper copies together (ICF), but it cannot prove the
+//! kernel itself was duplicated — that's on the caller's `#[inline(always)]`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+/// Stamps `#[inline(never)] fn <kernel>_roulette_<pad>` wrapper functions for
+/// each `pad => align` pair in `$copies`, each forced to `align` bytes of
+/// alignment via `repr(align)` and padded with `pad` leading nops before
+/// calling through to `$kernel`. Padding with a distinct, per-copy constant
+/// is the same ICF-defeating trick `factorial` uses with `read_volatile(&N)`,
+/// just repurposed so the constant itself is the nop count.
+#[macro_export]
+macro_rules! align_roulette_copies {
+    ($kernel:ident, $input_ty:ty, $output_ty:ty, $($pad:expr => $align:expr),+ $(,)?) => {
+        $crate::paste::paste! {
+            $(
+                #[inline(never)]
+                #[repr(align($align))]
+                fn [<$kernel _roulette_ $pad>](input: $input_ty) -> $output_ty {
+                    unsafe { std::ptr::read_volatile(&$pad) };
+                    for _ in 0..$pad {
+                        // Dummy payload, same purpose as __asm_nops in main.rs:
+                        // forces this copy's code to differ in length from its
+                        // siblings so the linker can't fold them together.
+                        unsafe { std::arch::asm!("nop", options(nomem, nostack)) };
+                    }
+                    $kernel(input)
+                }
+            )+
+        }
+    };
+}
+
+/// Stamps out the roulette family via [`align_roulette_copies!`], measures
+/// every copy, sanity-checks that the copies actually landed at distinct
+/// addresses, and returns the [`Spread`] across them.
+///
+/// ```ignore
+/// #[inline(always)] // required: see the module docs
+/// fn my_kernel(x: u64) -> u64 { x.wrapping_mul(31) }
+/// let spread = align_roulette!(
+///     my_kernel, u64, u64, input = 100u64,
+///     copies = [0 => 16, 4 => 32, 8 => 64, 16 => 128, 32 => 256]
+/// );
+/// println!("{spread:?}");
+/// ```
+#[macro_export]
+macro_rules! align_roulette {
+    ($kernel:ident, $input_ty:ty, $output_ty:ty, input = $input:expr, copies = [$($pad:expr => $align:expr),+ $(,)?]) => {{
+        $crate::align_roulette_copies!($kernel, $input_ty, $output_ty, $($pad => $align),+);
+        $crate::paste::paste! {
+            let addrs: Vec<usize> = vec![$([<$kernel _roulette_ $pad>] as usize),+];
+            $crate::align_roulette::warn_if_folded(stringify!($kernel), &addrs);
+            let samples: Vec<u64> = vec![
+                $($crate::align_roulette::measure([<$kernel _roulette_ $pad>], $input)),+
+            ];
+        }
+        $crate::align_roulette::summarize(&samples)
+    }};
+}
+
+/// Minimum-of-samples runtime of calling `f(input)`. Same shape as `measure`
+/// in `main.rs`, minus the confidence-interval machinery: roulette copies are
+/// meant to be compared many at a time rather than pairwise.
+#[inline(never)]
+pub fn measure<Input: Copy, Output>(f: fn(Input) -> Output, input: Input) -> u64 {
+    const SAMPLES: usize = 2000;
+    const SAMPLE_SIZE: usize = 100;
+    let mut min = u64::max_value();
+
+    for _ in 0..(SAMPLES / 10) {
+        black_box(f(black_box(input)));
+    }
+
+    for _ in 0..SAMPLES {
+        let start = Instant::now();
+        for _ in 0..SAMPLE_SIZE {
+            black_box(f(black_box(input)));
+        }
+        let time = start.elapsed().as_nanos() as u64 / SAMPLE_SIZE as u64;
+        min = min.min(time);
+    }
+
+    min
+}
+
+/// Warns on stderr if two or more roulette copies landed at the exact same
+/// address. This only catches the linker folding whole wrapper copies
+/// together (ICF) and is not proof the kernel body got duplicated into each
+/// one — it's a cheap check against the most obvious failure mode, not a
+/// substitute for the `#[inline(always)]` contract documented on this module.
+pub fn warn_if_folded(kernel_name: &str, addrs: &[usize]) {
+    let mut sorted = addrs.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+    if sorted.len() != addrs.len() {
+        eprintln!(
+            "align_roulette!({kernel_name}): {} of {} copies share an address \
+             (the linker folded them together); the reported spread does not \
+             reflect real alignment sensitivity. Make sure `{kernel_name}` is \
+             `#[inline(always)]`.",
+            addrs.len() - sorted.len(),
+            addrs.len(),
+        );
+    }
+}
+
+/// Spread of measured minimum runtimes across a roulette family.
+#[derive(Debug)]
+pub struct Spread {
+    pub min_ns: u64,
+    pub max_ns: u64,
+    pub spread_pct: f64,
+}
+
+/// Reduces per-copy timings to a min/max/spread summary.
+pub fn summarize(samples: &[u64]) -> Spread {
+    let min_ns = *samples.iter().min().unwrap();
+    let max_ns = *samples.iter().max().unwrap();
+    let spread_pct = if min_ns > 0 {
+        (max_ns - min_ns) as f64 / min_ns as f64 * 100.0
+    } else {
+        0.0
+    };
+    Spread {
+        min_ns,
+        max_ns,
+        spread_pct,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn warn_if_folded_accepts_distinct_addresses() {
+        // Distinct addresses: only checks that the call does not panic. The
+        // warning goes to stderr, which this test does not capture.
+        warn_if_folded("ok_kernel", &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn summarize_computes_spread_percentage() {
+        let spread = summarize(&[100, 120, 110]);
+        assert_eq!(spread.min_ns, 100);
+        assert_eq!(spread.max_ns, 120);
+        assert!((spread.spread_pct - 20.0).abs() < 1e-9);
+    }
+}
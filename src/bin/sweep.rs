@@ -0,0 +1,98 @@
+#![feature(fn_align)]
+
+// The plain `main` binary only ever probes a single NOP_COUNT baked in at
+// compile time via `make_asm_nops!()`, so seeing how the alignment swing
+// reacts to loop-body length means recompiling once per value. This binary
+// instead bakes many loop-body lengths into one build and sweeps across all
+// of them at runtime, printing a curve of `(nop_count, max-min, spread %)`.
+//
+// The swept nop counts come from one list (`nops = [...]` below), fed to a single
+// macro invocation that both stamps the functions and builds the lookup
+// table `families()` reads from, so changing the range means editing one
+// line rather than keeping parallel lists in sync.
+
+use paste::paste;
+use rand::{seq::SliceRandom, thread_rng};
+use same_code_different_performance::align_roulette::measure;
+use std::io::{stderr, Write};
+
+/// Same shape as `factorial` in `main.rs`, but the nop-padding length is
+/// itself a const generic (`NOPS`) rather than coming from the `NOP_COUNT`
+/// env var, so a single binary can host a whole family of loop lengths.
+/// `make_asm_nops!()` can't be reused here since it bakes in exactly one
+/// compile-time `NOP_COUNT`; sweeping many lengths in one binary needs the
+/// count to vary per monomorphization instead.
+#[inline(always)]
+fn factorial<const N: u64, const NOPS: usize>(mut n: u64) -> u64 {
+    // Prevents the linker from collapsing identical-N copies into one (ICF).
+    unsafe { std::ptr::read_volatile(&N) };
+
+    let mut m = 1u64;
+    while n > 1 {
+        m = m.saturating_mul(n);
+        n -= 1;
+        for _ in 0..NOPS {
+            // Dummy payload: pads the loop body with NOPS nop instructions.
+            unsafe { std::arch::asm!("nop", options(nomem, nostack)) };
+        }
+    }
+    m
+}
+
+/// Stamps `factorial_<n>_<nops>` for every `n` in `ns` crossed with every
+/// `nops` in `nops`, and a `families()` function returning, for each `nops`,
+/// the `(n, fn)` pairs generated for it. `ns` and `nops` are each listed once
+/// here and nowhere else in the file.
+macro_rules! sweep_families {
+    (@stamp $nops:expr, [$($n:expr),+ $(,)?]) => {
+            paste! {
+                $(
+                    #[inline(never)]
+                    fn [<factorial_ $n _ $nops>](n: u64) -> u64 {
+                        factorial::<$n, $nops>(n)
+                    }
+                )+
+            }
+    };
+    (@table $nops:expr, [$($n:expr),+ $(,)?]) => { paste! { vec![ $( ($n, [<factorial_ $n _ $nops>] as fn(u64) -> u64) ),+ ] } };
+    (nops = [$($nops:expr),+ $(,)?], ns = $ns:tt) => {
+        $( sweep_families!(@stamp $nops, $ns); )+
+
+        fn families() -> Vec<(usize, Vec<(usize, fn(u64) -> u64)>)> {
+            paste! {
+                vec![ $( ($nops, sweep_families!(@table $nops, $ns)) ),+ ]
+            }
+        }
+    };
+}
+
+sweep_families!(nops = [0, 2, 4, 8, 12, 16, 24, 32, 48], ns = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+
+fn main() {
+    let mut rnd = thread_rng();
+
+    println!("nop_count,max_minus_min_ns,spread_pct");
+
+    for (nop_count, mut functions) in families() {
+        // randomizing function run order to get rid of the "first function is the slowest" effect
+        functions.shuffle(&mut rnd);
+
+        let mut min = u64::max_value();
+        let mut max = u64::min_value();
+
+        for (i, f) in functions.into_iter() {
+            let value = measure(f, 100u64);
+            writeln!(stderr(), "  factorial_{}_{} = {}", i, nop_count, value).unwrap();
+            min = min.min(value);
+            max = max.max(value);
+        }
+
+        let diff = max - min;
+        let spread_pct = if min > 0 {
+            diff as f64 / min as f64 * 100.0
+        } else {
+            0.0
+        };
+        println!("{},{},{:.2}", nop_count, diff, spread_pct);
+    }
+}
@@ -0,0 +1,13 @@
+#![feature(fn_align)]
+
+//! Library half of this crate: the reusable alignment-roulette machinery
+//! lives here so it can actually be depended on like the request asked for
+//! ("a library any user can drop their hot function into"). `main.rs` and
+//! the other binaries under `src/bin/` use it the same way an external crate
+//! would, via `same_code_different_performance::{align_roulette, ...}`.
+
+// Re-exported so `align_roulette!`/`align_roulette_copies!` can paste
+// identifiers from call sites outside this crate via `$crate::paste::paste!`.
+pub use paste;
+
+pub mod align_roulette;
@@ -1,9 +1,13 @@
 #![feature(fn_align)]
+#![feature(portable_simd)]
 
 use paste::paste;
-use same_code_different_performance::make_asm_nops;
+use same_code_different_performance::{align_roulette, make_asm_nops};
 use std::{hint::black_box, io::Write, time::Instant};
 
+#[cfg(feature = "perf-counters")]
+mod perf;
+
 // Creates __asm_nops() functions with sequence of NOP instructions. The number of instructions
 // is given in NOP_COUNT env variable at compile time
 make_asm_nops!();
@@ -83,6 +87,28 @@ criterion::criterion_group!(benches, criterion_support::bench);
 #[cfg(feature = "criterion")]
 criterion::criterion_main!(benches);
 
+/// Where a function's hot loop lands relative to the CPU's instruction-fetch
+/// windows, derived from its own address. This is what actually explains the
+/// max-min swing: the functions are bit-identical, only their placement differs.
+struct Alignment {
+    addr: usize,
+    offset_16: usize,
+    offset_32: usize,
+    offset_64: usize,
+}
+
+impl Alignment {
+    fn of(f: fn(u64) -> u64) -> Self {
+        let addr = f as usize;
+        Alignment {
+            addr,
+            offset_16: addr % 16,
+            offset_32: addr % 32,
+            offset_64: addr % 64,
+        }
+    }
+}
+
 #[cfg(not(feature = "criterion"))]
 fn main() {
     use rand::{seq::SliceRandom, thread_rng};
@@ -110,46 +136,266 @@ fn main() {
     // randomizing function run order to get rid of the "first function is the slowest" effect
     functions.shuffle(&mut rnd);
 
+    let mut results = Vec::with_capacity(functions.len());
+
     for (i, f) in functions.into_iter() {
-        let value = measure(f);
-        writeln!(stderr(), "factorial_{} = {}", i, value).unwrap();
-        min = min.min(value);
-        max = max.max(value);
+        let stats = measure(f);
+        let alignment = Alignment::of(f);
+        writeln!(
+            stderr(),
+            "factorial_{} = [{}, {}, {}] ns (point/low/high; addr={:#x}, offset mod 16/32/64 = {}/{}/{})",
+            i,
+            stats.point,
+            stats.low,
+            stats.high,
+            alignment.addr,
+            alignment.offset_16,
+            alignment.offset_32,
+            alignment.offset_64
+        )
+        .unwrap();
+        #[cfg(feature = "perf-counters")]
+        report_perf_counters(i, f);
+        min = min.min(stats.point);
+        max = max.max(stats.point);
+        results.push((i, stats, alignment));
     }
 
+    // Correlate the measured time against the 64-byte cache-line offset: if the
+    // swing really is placement-driven, time should track offset_64, not `i`.
+    writeln!(stderr(), "\naddress correlation (sorted by offset mod 64):").unwrap();
+    results.sort_by_key(|(_, _, a)| a.offset_64);
+    for (i, stats, alignment) in &results {
+        writeln!(
+            stderr(),
+            "  offset_64={:>2} cache_line={:<2} fetch_window={:<2} factorial_{} = {} ns",
+            alignment.offset_64,
+            alignment.addr / 64 % 2,
+            alignment.addr / 32 % 2,
+            i,
+            stats.point
+        )
+        .unwrap();
+    }
+    writeln!(
+        stderr(),
+        "pearson r(offset_64, time) = {:.3}\n",
+        correlation(&results)
+    )
+    .unwrap();
+
     println!(
         "NOP_COUNT={} max-min difference = {}",
         nop_count!(),
         max - min
-    )
+    );
+
+    // A tiny smoke test for the generalized `align_roulette!` machinery
+    // (see `align_roulette.rs`): any `fn(Input) -> Output` kernel can be
+    // checked for placement-driven swings, not just `factorial`.
+    let spread = align_roulette!(
+        square,
+        u64,
+        u64,
+        input = 100u64,
+        copies = [0 => 16, 4 => 32, 8 => 64, 16 => 128, 32 => 256]
+    );
+    writeln!(stderr(), "\nalign_roulette!(square) = {:?}", spread).unwrap();
+
+    // Same check, but against a floating-point SIMD kernel (one 8-lane
+    // weighted dot product per call) rather than an integer/nop-padded loop.
+    let window = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+    let spread = align_roulette!(
+        moving_average,
+        [f32; TAPS],
+        f32,
+        input = window,
+        copies = [0 => 16, 4 => 32, 8 => 64, 16 => 128, 32 => 256]
+    );
+    writeln!(stderr(), "align_roulette!(moving_average) = {:?}", spread).unwrap();
+}
+
+// Marked #[inline(always)] per the contract documented on `align_roulette!`:
+// without it, the linker could keep this body as a single shared symbol and
+// every roulette copy would just call through to it. Unlike `main`, its only
+// caller, it is not gated on the `criterion` feature.
+#[inline(always)]
+fn square(x: u64) -> u64 {
+    black_box(x).wrapping_mul(x)
+}
+
+/// Number of taps in the `moving_average` filter below.
+const TAPS: usize = 8;
+
+/// Savitzky-Golay-style smoothing weights for a `TAPS`-wide window. Compile-time
+/// constants, same role as `NOP_COUNT` for `factorial`: the coefficients never
+/// change, only where the linker places this function's code does.
+const COEFFS: [f32; TAPS] = [0.05, 0.1, 0.15, 0.2, 0.2, 0.15, 0.1, 0.05];
+
+/// One step of a fixed-width weighted moving-average filter: the dot product
+/// of a single `TAPS`-wide window against `COEFFS`, vectorized with
+/// `std::simd`. Each call yields one output sample; it does not slide the
+/// window over a longer signal and contains no loop of its own.
+///
+/// Marked #[inline(always)] per the contract documented on `align_roulette!`.
+/// Not gated on the `criterion` feature: it's exercised directly by unit
+/// tests regardless of that feature.
+#[inline(always)]
+fn moving_average(window: [f32; TAPS]) -> f32 {
+    use std::simd::{f32x8, num::SimdFloat};
+
+    let window = f32x8::from_array(window);
+    let coeffs = f32x8::from_array(COEFFS);
+    (window * coeffs).reduce_sum()
+}
+
+/// Pearson correlation coefficient between `offset_64` and measured time,
+/// used to show that the swing tracks placement rather than being noise.
+#[cfg(not(feature = "criterion"))]
+fn correlation(results: &[(usize, ConfidenceInterval, Alignment)]) -> f64 {
+    let n = results.len() as f64;
+    let xs: Vec<f64> = results.iter().map(|(_, _, a)| a.offset_64 as f64).collect();
+    let ys: Vec<f64> = results.iter().map(|(_, s, _)| s.point as f64).collect();
+
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+
+    let cov: f64 = xs
+        .iter()
+        .zip(&ys)
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let var_x: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let var_y: f64 = ys.iter().map(|y| (y - mean_y).powi(2)).sum();
+
+    if var_x == 0.0 || var_y == 0.0 {
+        0.0
+    } else {
+        cov / (var_x.sqrt() * var_y.sqrt())
+    }
+}
+
+/// `point` (the observed sample minimum) plus a bootstrap bound on how much
+/// that minimum might be underestimating the function's true minimum
+/// runtime, so a caller can tell whether two functions' timings are
+/// statistically distinguishable or within noise.
+///
+/// This is one-sided, not a two-sided interval around `point`: every
+/// resample is drawn with replacement from the retained samples themselves,
+/// so no resample minimum can ever fall below `point`, which means
+/// `point <= low <= high` always (see `bootstrap_ci_cannot_beat_the_observed_minimum`).
+/// `low`/`high` are the 2.5/97.5 percentiles of that one-sided distribution,
+/// not a lower/upper bound straddling `point`.
+struct ConfidenceInterval {
+    low: u64,
+    point: u64,
+    high: u64,
 }
 
 #[cfg(not(feature = "criterion"))]
 #[inline(never)]
-fn measure(f: fn(u64) -> u64) -> u64 {
+fn measure(f: fn(u64) -> u64) -> ConfidenceInterval {
     const SAMPLES: usize = 10000;
     const SAMPLE_SIZE: usize = 100;
-    let mut min = u64::max_value();
+    const BOOTSTRAP_RESAMPLES: usize = 10000;
 
     // Warm up iterations to familiarize CPU with the code
     for _ in 0..(SAMPLES / 10) {
         black_box(f(black_box(100)));
     }
 
+    let mut samples = Vec::with_capacity(SAMPLES);
     for _ in 0..SAMPLES {
         let time = Instant::now();
         for _ in 0..SAMPLE_SIZE {
             black_box(f(black_box(100)));
         }
         let time = time.elapsed().as_nanos() as u64 / SAMPLE_SIZE as u64;
+        samples.push(time);
+    }
+
+    let retained = tukey_filter(samples);
+
+    // Measuring minimum execution time as a measure of the performance.
+    // For more information about why and when it is appropriate see:
+    //  https://betterprogramming.pub/the-mean-misleads-why-the-minimum-is-the-true-measure-of-a-functions-run-time-47fa079075b0
+    let point = *retained.iter().min().unwrap();
+    let (low, high) = bootstrap_ci(&retained, BOOTSTRAP_RESAMPLES, &mut rand::thread_rng());
+
+    ConfidenceInterval { low, point, high }
+}
 
-        // Measuring minimum execution time as a measure of the performance.
-        // For more information about why and when it is appropriate see:
-        //  https://betterprogramming.pub/the-mean-misleads-why-the-minimum-is-the-true-measure-of-a-functions-run-time-47fa079075b0
-        min = min.min(time);
+/// Discards startup/warm-up outliers using Tukey fences (below Q1-1.5*IQR or
+/// above Q3+1.5*IQR), so a handful of scheduler-preempted samples can't
+/// distort the bootstrap in [`bootstrap_ci`].
+fn tukey_filter(mut samples: Vec<u64>) -> Vec<u64> {
+    samples.sort_unstable();
+    let q1 = samples[samples.len() / 4] as f64;
+    let q3 = samples[samples.len() * 3 / 4] as f64;
+    let iqr = q3 - q1;
+    let (lower_fence, upper_fence) = (q1 - 1.5 * iqr, q3 + 1.5 * iqr);
+    samples
+        .into_iter()
+        .filter(|&s| (s as f64) >= lower_fence && (s as f64) <= upper_fence)
+        .collect()
+}
+
+/// Bootstraps a confidence interval on the minimum of `retained`: resamples
+/// it with replacement `resamples` times, takes the per-resample minimum,
+/// and returns the 2.5/97.5 percentiles of that distribution.
+fn bootstrap_ci(retained: &[u64], resamples: usize, rng: &mut impl rand::Rng) -> (u64, u64) {
+    let mut resample_minimums = Vec::with_capacity(resamples);
+    for _ in 0..resamples {
+        let resample_min = (0..retained.len())
+            .map(|_| retained[rng.gen_range(0..retained.len())])
+            .min()
+            .unwrap();
+        resample_minimums.push(resample_min);
     }
+    resample_minimums.sort_unstable();
 
-    min
+    let low = resample_minimums[(resample_minimums.len() as f64 * 0.025) as usize];
+    let high = resample_minimums[(resample_minimums.len() as f64 * 0.975) as usize];
+    (low, high)
+}
+
+/// Runs `f` under the hardware counter group (if `PERF_COUNTERS=1`) and
+/// prints retired cycles/instructions/CPI and frontend-stall counts, which
+/// isolate the alignment penalty far more cleanly than wall-clock nanoseconds.
+/// See `perf::PerfCounters::measure` for how bracket overhead is amortized.
+#[cfg(feature = "perf-counters")]
+fn report_perf_counters(i: usize, f: fn(u64) -> u64) {
+    use std::io::stderr;
+
+    if !perf::enabled() {
+        return;
+    }
+
+    let sample = perf::PerfCounters::new().and_then(|mut counters| {
+        counters.measure(|| {
+            black_box(f(black_box(100)));
+        })
+    });
+
+    match sample {
+        Ok(sample) => writeln!(
+            stderr(),
+            "    factorial_{}: cycles={} instructions={} cpi={:.3} frontend_stalls={} dsb_miss={}{}",
+            i,
+            sample.cycles,
+            sample.instructions,
+            sample.cycles_per_instruction(),
+            sample.frontend_stalls,
+            sample.dsb_miss,
+            if sample.dsb_miss_is_meaningful {
+                ""
+            } else {
+                " (not Skylake-family: dsb_miss is meaningless on this CPU)"
+            }
+        )
+        .unwrap(),
+        Err(err) => writeln!(stderr(), "    factorial_{}: perf_event unavailable: {}", i, err).unwrap(),
+    }
 }
 
 #[cfg(test)]
@@ -163,4 +409,37 @@ mod test {
             assert_eq!(factorial_1(i), factorial_10(i));
         }
     }
+
+    #[test]
+    fn tukey_filter_drops_outliers() {
+        let samples = vec![100, 101, 99, 102, 98, 100, 100, 103, 97, 100, 100_000];
+        let retained = tukey_filter(samples);
+        assert!(!retained.contains(&100_000));
+        assert!(retained.contains(&97));
+    }
+
+    #[test]
+    fn bootstrap_ci_cannot_beat_the_observed_minimum() {
+        // Every resample is drawn, with replacement, from `samples` itself, so
+        // no resample minimum can be smaller than `samples`'s own minimum:
+        // `point` is a hard lower bound for the whole bootstrap distribution.
+        let samples: Vec<u64> = vec![100, 101, 99, 102, 98, 100, 100, 103, 97, 100];
+        let point = *samples.iter().min().unwrap();
+        let (low, high) = bootstrap_ci(&samples, 2000, &mut rand::thread_rng());
+        assert!(point <= low, "point {} should be <= low {}", point, low);
+        assert!(low <= high, "low {} should be <= high {}", low, high);
+    }
+
+    #[test]
+    fn moving_average_matches_hand_computed_dot_product() {
+        let window = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        let expected: f32 = window.iter().zip(COEFFS.iter()).map(|(w, c)| w * c).sum();
+        assert!(
+            (moving_average(window) - expected).abs() < 1e-6,
+            "moving_average({:?}) = {}, expected {}",
+            window,
+            moving_average(window),
+            expected
+        );
+    }
 }
@@ -0,0 +1,163 @@
+//! Optional hardware-performance-counter backend, enabled with the
+//! `perf-counters` feature and opted into at runtime via `PERF_COUNTERS=1`
+//! (see [`enabled`]).
+//!
+//! Wall-clock minimums conflate the alignment effect with scheduler noise and
+//! turbo-state transitions. Since every `factorial_n` retires the exact same
+//! instruction count, cycles-per-instruction and frontend-stall counts
+//! isolate the alignment penalty far more cleanly than nanoseconds — but only
+//! if the counter reads themselves aren't dominated by the `enable`/`disable`/
+//! `read` ioctls, which is why [`PerfCounters::measure`] amortizes each
+//! bracket over many calls and takes the minimum over many brackets, the same
+//! way the wall-clock `measure` in `main.rs` does.
+
+use perf_event::events::{Hardware, Raw};
+use perf_event::{Builder, Counter, Group};
+use std::io;
+
+/// `frontend_retired.dsb_miss` raw event (Skylake-family encoding), used as a
+/// proxy for DSB-vs-MITE (decoded-stream-buffer vs legacy decode) switches.
+/// Only meaningful on that microarchitecture family; see [`looks_like_skylake_family`].
+const DSB_MISS_RAW: u64 = 0x01_9c;
+
+/// How many times `body()` runs inside a single enable/disable bracket. Each
+/// bracket costs a handful of ioctls plus a `read`, likely more than the cost
+/// of one `factorial_n(100)` call, so the raw per-call counts would otherwise
+/// be dominated by that overhead rather than by `body` itself.
+const REPEAT: u64 = 1000;
+
+/// How many brackets to take the minimum over, to average out scheduler and
+/// turbo-state noise the same way the wall-clock harness does.
+const SAMPLES: usize = 100;
+
+/// Brackets run before any are recorded, to warm up caches/branch predictors.
+const WARMUP_SAMPLES: usize = 10;
+
+pub struct PerfCounters {
+    group: Group,
+    cycles: Counter,
+    instructions: Counter,
+    frontend_stalls: Counter,
+    dsb_miss: Counter,
+    dsb_miss_is_meaningful: bool,
+}
+
+pub struct Sample {
+    pub cycles: u64,
+    pub instructions: u64,
+    pub frontend_stalls: u64,
+    pub dsb_miss: u64,
+    /// Whether `dsb_miss` was read on a microarchitecture where the raw event
+    /// code above is known to mean what it claims. If `false`, `dsb_miss` is
+    /// still printed but is whatever unrelated counter `0x19c` happens to be
+    /// on this CPU, and should not be interpreted.
+    pub dsb_miss_is_meaningful: bool,
+}
+
+impl Sample {
+    pub fn cycles_per_instruction(&self) -> f64 {
+        self.cycles as f64 / self.instructions.max(1) as f64
+    }
+}
+
+impl PerfCounters {
+    pub fn new() -> io::Result<Self> {
+        let mut group = Group::new()?;
+        let cycles = Builder::new()
+            .group(&mut group)
+            .kind(Hardware::CPU_CYCLES)
+            .build()?;
+        let instructions = Builder::new()
+            .group(&mut group)
+            .kind(Hardware::INSTRUCTIONS)
+            .build()?;
+        let frontend_stalls = Builder::new()
+            .group(&mut group)
+            .kind(Hardware::STALLED_CYCLES_FRONTEND)
+            .build()?;
+        let dsb_miss = Builder::new()
+            .group(&mut group)
+            .kind(Raw::new(DSB_MISS_RAW))
+            .build()?;
+
+        Ok(PerfCounters {
+            group,
+            cycles,
+            instructions,
+            frontend_stalls,
+            dsb_miss,
+            dsb_miss_is_meaningful: looks_like_skylake_family(),
+        })
+    }
+
+    /// Runs `body` under the counter group, amortizing bracket overhead over
+    /// `REPEAT` calls and taking the minimum (by cycles) over `SAMPLES`
+    /// brackets, after `WARMUP_SAMPLES` untimed brackets.
+    pub fn measure(&mut self, mut body: impl FnMut()) -> io::Result<Sample> {
+        for _ in 0..WARMUP_SAMPLES {
+            for _ in 0..REPEAT {
+                body();
+            }
+        }
+
+        let mut best: Option<Sample> = None;
+        for _ in 0..SAMPLES {
+            self.group.reset()?;
+            self.group.enable()?;
+            for _ in 0..REPEAT {
+                body();
+            }
+            self.group.disable()?;
+
+            let counts = self.group.read()?;
+            let sample = Sample {
+                cycles: counts[&self.cycles] / REPEAT,
+                instructions: counts[&self.instructions] / REPEAT,
+                frontend_stalls: counts[&self.frontend_stalls] / REPEAT,
+                dsb_miss: counts[&self.dsb_miss] / REPEAT,
+                dsb_miss_is_meaningful: self.dsb_miss_is_meaningful,
+            };
+            best = Some(match best {
+                Some(prev) if prev.cycles <= sample.cycles => prev,
+                _ => sample,
+            });
+        }
+
+        Ok(best.expect("SAMPLES > 0"))
+    }
+}
+
+/// Intel family-6 `model` numbers that share the Skylake-generation frontend
+/// `DSB_MISS_RAW` encoding: 78/94 (Skylake/Skylake-X), 85 (Skylake-SP/Cascade
+/// Lake/Cooper Lake server), 142/158 (Kaby Lake/Coffee Lake, same frontend as
+/// client Skylake). Family 6 alone spans nearly every Intel CPU made since
+/// Core 2, so the model number is what actually narrows this down.
+const SKYLAKE_FAMILY_MODELS: [u32; 5] = [78, 94, 85, 142, 158];
+
+/// Best-effort check that this looks like an Intel Skylake-family CPU, since
+/// `DSB_MISS_RAW` (`0x19c`) is only known to mean "frontend_retired.dsb_miss"
+/// there. On anything else (AMD, other Intel generations, non-x86) the same
+/// raw event code reads a different, unrelated counter.
+fn looks_like_skylake_family() -> bool {
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+    let field = |name: &str| -> Option<&str> {
+        cpuinfo
+            .lines()
+            .find(|l| l.trim_start().starts_with(name))
+            .and_then(|l| l.split(':').nth(1))
+            .map(|v| v.trim())
+    };
+
+    let is_intel = field("vendor_id") == Some("GenuineIntel");
+    let family_6 = field("cpu family") == Some("6");
+    let model = field("model").and_then(|v| v.parse::<u32>().ok());
+
+    is_intel && family_6 && model.is_some_and(|m| SKYLAKE_FAMILY_MODELS.contains(&m))
+}
+
+/// Runtime opt-in on top of the `perf-counters` feature, so a binary built
+/// with it can still fall back to wall-clock timing on machines without
+/// perf_event access (containers, CI, non-Linux).
+pub fn enabled() -> bool {
+    std::env::var("PERF_COUNTERS").as_deref() == Ok("1")
+}